@@ -6,22 +6,119 @@
 // copied, modified, or distributed except according to those terms.
 
 //! Some basic utilities used throughout the allocator code.
+//!
+//! The module compiles in two modes. By default it is built against `std` and
+//! uses the host `mmap_alloc`/`sysconf` page allocator. With the `no_std`
+//! feature it switches the core types over to `core` and routes every
+//! page-size/mapping call through a platform-supplied [`mmap::PageSource`] so
+//! that kernel and embedded targets can plug in their own raw page allocator.
+//! In both modes the page source is held in a `spin` once rather than a
+//! `lazy_static` singleton.
+#[cfg(not(feature = "no_std"))]
 use std::cmp;
+#[cfg(feature = "no_std")]
+use core::cmp;
+
+#[cfg(not(feature = "no_std"))]
 use std::ops::{Deref, DerefMut};
+#[cfg(feature = "no_std")]
+use core::ops::{Deref, DerefMut};
+
+#[cfg(not(feature = "no_std"))]
 use std::cell::UnsafeCell;
+#[cfg(feature = "no_std")]
+use core::cell::UnsafeCell;
 
 pub mod mmap {
-    use mmap_alloc::{MapAlloc, MapAllocBuilder};
-    use alloc::alloc::{Alloc, Layout};
+    /// An abstraction over a platform's raw page allocator.
+    ///
+    /// Hosted builds use the [`HostPageSource`] default, which delegates to
+    /// `mmap_alloc`/`sysconf`. `no_std` targets register their own
+    /// implementation with [`set_page_source`] before the first allocation.
+    pub trait PageSource: Sync {
+        fn page_size(&self) -> usize;
+        unsafe fn map(&self, size: usize) -> Option<*mut u8>;
+        unsafe fn unmap(&self, p: *mut u8, size: usize);
+        unsafe fn commit(&self, p: *mut u8, size: usize);
+        unsafe fn uncommit(&self, p: *mut u8, size: usize);
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    pub use self::hosted::{host, HostPageSource};
+
+    #[cfg(not(feature = "no_std"))]
+    mod hosted {
+        use super::PageSource;
+        use alloc::alloc::{Alloc, Layout};
+        use mmap_alloc::{MapAlloc, MapAllocBuilder};
+        use spin::Once;
+
+        /// The default `PageSource` for hosted builds, preserving the original
+        /// `mmap_alloc`-backed behavior.
+        pub struct HostPageSource {
+            alloc: MapAlloc,
+        }
+
+        static MMAP: Once<HostPageSource> = Once::new();
+
+        /// Lazily construct and return the process-wide host page source. This
+        /// replaces the old `lazy_static!` singleton with a `spin::Once`.
+        pub fn host() -> &'static HostPageSource {
+            MMAP.call_once(|| HostPageSource {
+                alloc: MapAllocBuilder::default().commit(cfg!(windows)).build(),
+            })
+        }
+
+        fn layout_for_size(size: usize) -> Layout {
+            Layout::from_size_align(size, ::sysconf::page::pagesize()).unwrap()
+        }
+
+        impl PageSource for HostPageSource {
+            fn page_size(&self) -> usize {
+                ::sysconf::page::pagesize()
+            }
+
+            unsafe fn map(&self, size: usize) -> Option<*mut u8> {
+                (&self.alloc).alloc(layout_for_size(size)).ok()
+            }
+
+            unsafe fn unmap(&self, p: *mut u8, size: usize) {
+                (&self.alloc).dealloc(p, layout_for_size(size));
+            }
+
+            unsafe fn commit(&self, p: *mut u8, size: usize) {
+                (&self.alloc).commit(p, layout_for_size(size))
+            }
 
-    lazy_static!{ 
-        static ref MMAP: MapAlloc = MapAllocBuilder::default()
-            .commit(cfg!(windows))
-            .build();
+            unsafe fn uncommit(&self, p: *mut u8, size: usize) {
+                (&self.alloc).uncommit(p, layout_for_size(size));
+            }
+        }
+    }
+
+    // The active page source. On hosted builds it defaults to `HostPageSource`;
+    // on `no_std` builds the platform must install one via `set_page_source`.
+    use spin::Once;
+    static SOURCE: Once<&'static dyn PageSource> = Once::new();
+
+    /// Install the platform's page source. Only the first call takes effect,
+    /// mirroring the one-shot nature of the old static singleton.
+    pub fn set_page_source(src: &'static dyn PageSource) {
+        SOURCE.call_once(|| src);
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    fn source() -> &'static dyn PageSource {
+        *SOURCE.call_once(|| host() as &'static dyn PageSource)
+    }
+
+    #[cfg(feature = "no_std")]
+    fn source() -> &'static dyn PageSource {
+        *SOURCE.get().expect("no PageSource installed; call mmap::set_page_source first")
     }
 
     pub fn page_size() -> usize {
-        ::sysconf::page::pagesize()
+        source().page_size()
     }
 
     pub fn map(size: usize) -> *mut u8 {
@@ -29,30 +126,39 @@ pub mod mmap {
     }
 
     pub fn fallible_map(size: usize) -> Option<*mut u8> {
-        unsafe { (&*MMAP).alloc(layout_for_size(size)).ok() }
+        unsafe { source().map(size) }
     }
 
     pub unsafe fn unmap(p: *mut u8, size: usize) {
-        (&*MMAP).dealloc(p, layout_for_size(size));
+        source().unmap(p, size);
     }
 
     pub unsafe fn commit(p: *mut u8, size: usize) {
-        (&*MMAP).commit(p, layout_for_size(size))
+        source().commit(p, size)
     }
 
     pub unsafe fn uncommit(p: *mut u8, size: usize) {
-        (&*MMAP).uncommit(p, layout_for_size(size));
+        source().uncommit(p, size);
     }
-
-    fn layout_for_size(size: usize) -> Layout {
-        Layout::from_size_align(size, page_size()).unwrap()
-     }
 }
 
+/// An allocation request could not be satisfied by the underlying `mmap`.
+///
+/// Returned by the `try_*` constructors so that subsystems layered on top of
+/// `TypedArray` can propagate allocation failure instead of aborting the
+/// process.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AllocError;
+
+/// A byte offset did not satisfy the alignment required by the destination
+/// type, so a typed copy could not be performed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UnalignedError;
+
 // we use the unlikely intrinsic if it is available.
 
 #[cfg(feature = "nightly")]
-pub use std::intrinsics::{likely, unlikely};
+pub use core::intrinsics::{likely, unlikely};
 
 #[cfg(not(feature = "nightly"))]
 #[cfg_attr(feature = "cargo-clippy", allow(inline_always))]
@@ -108,6 +214,38 @@ impl<T: LazyInitializable> Lazy<T> {
             val: UnsafeCell::new(None),
         }
     }
+
+    /// Returns `true` if the value has already been initialized.
+    pub fn filled(&self) -> bool {
+        unsafe { (*self.val.get()).is_some() }
+    }
+
+    /// Return a reference to the value only if it is already initialized.
+    ///
+    /// Unlike `deref`, this never forces initialization, so it is safe to use
+    /// for diagnostics or stats that should not pay the construction cost.
+    pub fn try_get(&self) -> Option<&T> {
+        unsafe { (*self.val.get()).as_ref() }
+    }
+
+    /// Initialize the value with `f` if it is not yet filled, propagating any
+    /// error `f` returns instead of panicking inside `T::init`.
+    pub fn get_or_try_init<E, F>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce(&T::Params) -> Result<T, E>,
+    {
+        let state = unsafe { &mut *self.val.get() };
+        if state.is_none() {
+            *state = Some(f(&self.params)?);
+        }
+        Ok(state.as_ref().unwrap())
+    }
+
+    /// Replace the current value, returning the previous one if any.
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        use core::mem;
+        unsafe { mem::replace(&mut *self.val.get(), Some(value)) }
+    }
 }
 
 impl<T: LazyInitializable> Deref for Lazy<T> {
@@ -137,6 +275,76 @@ impl<T: LazyInitializable> DerefMut for Lazy<T> {
 }
 
 
+#[cfg(not(feature = "no_std"))]
+use std::sync::Once;
+
+/// A thread-safe counterpart to `Lazy`.
+///
+/// Where `Lazy` initializes inside an `UnsafeCell` on first `deref` — which
+/// races when shared across threads — `SyncLazy` guards the stored value with
+/// a `std::sync::Once` so that exactly one thread runs `T::init` while the
+/// others block until that initialization has completed. It is kept a separate
+/// type so that the single-threaded `Lazy` stays zero-overhead.
+///
+/// The `Clone` semantics match `Lazy`: cloning copies the constructor
+/// parameters and resets the value to uninitialized.
+///
+/// Only available on hosted builds, since it relies on `std::sync::Once`.
+#[cfg(not(feature = "no_std"))]
+pub struct SyncLazy<T: LazyInitializable> {
+    params: T::Params,
+    once: Once,
+    val: UnsafeCell<Option<T>>,
+}
+
+// `Once` serializes initialization, so sharing is sound as long as the value
+// and its parameters are themselves shareable.
+#[cfg(not(feature = "no_std"))]
+unsafe impl<T: LazyInitializable + Sync> Sync for SyncLazy<T> where T::Params: Sync {}
+#[cfg(not(feature = "no_std"))]
+unsafe impl<T: LazyInitializable + Send> Send for SyncLazy<T> where T::Params: Send {}
+
+#[cfg(not(feature = "no_std"))]
+impl<T: LazyInitializable> SyncLazy<T> {
+    /// Create a new `SyncLazy<T>` with constructor parameters given by `params`.
+    pub fn new(params: T::Params) -> Self {
+        SyncLazy {
+            params: params,
+            once: Once::new(),
+            val: UnsafeCell::new(None),
+        }
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl<T: LazyInitializable> Clone for SyncLazy<T>
+where
+    T::Params: Clone,
+{
+    fn clone(&self) -> Self {
+        SyncLazy {
+            params: self.params.clone(),
+            once: Once::new(),
+            val: UnsafeCell::new(None),
+        }
+    }
+}
+
+#[cfg(not(feature = "no_std"))]
+impl<T: LazyInitializable> Deref for SyncLazy<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        let val = self.val.get();
+        self.once.call_once(|| unsafe {
+            *val = Some(T::init(&self.params));
+        });
+        // `call_once` has returned, so the write above happened-before this
+        // read and the slot is `Some`.
+        unsafe { (*val).as_ref().unwrap() }
+    }
+}
+
 /// A low-level dynamic collection of `T` values.
 ///
 /// `TypedArray` uses mmap for memory allocation. This means that memory consumption from a
@@ -155,17 +363,50 @@ pub struct TypedArray<T> {
 
 impl<T> TypedArray<T> {
     pub fn new(size: usize) -> TypedArray<T> {
-        use std::mem::size_of;
+        TypedArray::try_new(size).expect("mmap should not fail")
+    }
+
+    /// Like `new`, but returns `Err(AllocError)` instead of aborting when the
+    /// backing `mmap` fails. Built on `mmap::fallible_map` so that callers in
+    /// recovery-capable or embedded contexts can propagate the failure.
+    pub fn try_new(size: usize) -> Result<TypedArray<T>, AllocError> {
+        use core::mem::size_of;
         let page_size = mmap::page_size();
         let bytes = size_of::<T>() * size;
         let rem = bytes % page_size;
         let n_pages = bytes / page_size + cmp::min(1, rem);
         let region_size = n_pages * page_size;
-        let mem = mmap::map(region_size);
-        TypedArray {
+        let mem = mmap::fallible_map(region_size).ok_or(AllocError)?;
+        Ok(TypedArray {
             data: mem as *mut T,
             len: size,
             mapped: region_size,
+        })
+    }
+
+    /// Like `new`, but sets `len` to the full page-rounded `capacity()` so that
+    /// the slack slots in the last mapped page become usable instead of wasted.
+    ///
+    /// This is opt-in: `new`/`try_new` keep `len == size` so that existing
+    /// callers (and `iter()`) still see exactly the requested element count.
+    pub fn with_capacity(size: usize) -> TypedArray<T> {
+        let mut res = TypedArray::new(size);
+        res.len = res.capacity();
+        res
+    }
+
+    /// The number of `T`-sized slots actually backed by the mapped region.
+    ///
+    /// Because `new`/`try_new` round the request up to a whole number of
+    /// pages, this is always `>=` the requested element count. For
+    /// zero-sized types the requested count is returned unchanged.
+    pub fn capacity(&self) -> usize {
+        use core::mem::size_of;
+        let elem = size_of::<T>();
+        if elem == 0 {
+            self.len
+        } else {
+            self.mapped / elem
         }
     }
 
@@ -182,10 +423,75 @@ impl<T> TypedArray<T> {
         self.data.offset(n as isize)
     }
 
+    /// Get an index into the array, returning `None` if `n` is out of bounds.
+    ///
+    /// The checked counterpart to `get`; safe because it verifies `n < len`
+    /// before forming the pointer.
+    pub fn try_get(&self, n: usize) -> Option<*mut T> {
+        if n < self.len {
+            Some(unsafe { self.get(n) })
+        } else {
+            None
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
 
+    /// Write `value` into slot `n`, bounds-checking `n < len` first.
+    ///
+    /// The checked counterpart to the raw `get` + `ptr::write` idiom. The slot
+    /// is overwritten in place; any value previously written there is *not*
+    /// dropped.
+    pub fn write(&self, n: usize, value: T) {
+        use core::ptr;
+        assert!(n < self.len, "TypedArray::write index out of bounds");
+        unsafe { ptr::write(self.get(n), value) }
+    }
+
+    /// Bulk-copy `src` into the array starting at element `start`, checking
+    /// that `start + src.len() <= len`. The regions are non-overlapping
+    /// because `src` is a separate borrow.
+    pub fn copy_from_slice(&self, start: usize, src: &[T])
+    where
+        T: Copy,
+    {
+        use core::ptr;
+        assert!(
+            start + src.len() <= self.len,
+            "TypedArray::copy_from_slice out of bounds"
+        );
+        unsafe { ptr::copy_nonoverlapping(src.as_ptr(), self.get(start), src.len()) }
+    }
+
+    /// Copy raw bytes into the array at `byte_offset`, first verifying that the
+    /// destination address is aligned for `T`. Returns the offset of the next
+    /// free byte on success, or `UnalignedError` if the address is unaligned.
+    ///
+    /// Because the mmap region is zero-initialized on first touch, bytes left
+    /// untouched by such copies read back as zero.
+    pub fn copy_align_from_bytes(
+        &self,
+        byte_offset: usize,
+        src: &[u8],
+    ) -> Result<usize, UnalignedError> {
+        use core::mem::align_of;
+        use core::ptr;
+        // Bounds-check before forming the pointer: computing an out-of-allocation
+        // pointer with `offset` is UB even without a deref.
+        assert!(
+            byte_offset + src.len() <= self.mapped,
+            "TypedArray::copy_align_from_bytes out of bounds"
+        );
+        let dst = unsafe { (self.data as *mut u8).offset(byte_offset as isize) };
+        if (dst as usize) % align_of::<T>() != 0 {
+            return Err(UnalignedError);
+        }
+        unsafe { ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len()) }
+        Ok(byte_offset + src.len())
+    }
+
     pub unsafe fn destroy(&self) {
         mmap::unmap(self.data as *mut u8, self.mapped);
     }
@@ -198,6 +504,12 @@ impl<T> OwnedArray<T> {
     pub fn new(size: usize) -> OwnedArray<T> {
         OwnedArray(TypedArray::new(size))
     }
+
+    /// The fallible counterpart to `new`, propagating `AllocError` from the
+    /// underlying `TypedArray::try_new`.
+    pub fn try_new(size: usize) -> Result<OwnedArray<T>, AllocError> {
+        Ok(OwnedArray(TypedArray::try_new(size)?))
+    }
 }
 
 impl<T> Deref for OwnedArray<T> {
@@ -233,6 +545,276 @@ impl<'a, T: 'a> Iterator for TypedArrayIter<'a, T> {
     }
 }
 
+/// Bump-pointer arena allocators layered on the mmap-backed `TypedArray`.
+///
+/// Modeled on `rustc_arena`: allocation is a pointer bump within the current
+/// mapped chunk, and memory is reclaimed all at once when the arena is
+/// dropped. When a chunk is exhausted a new, larger `TypedArray` chunk is
+/// mapped and the old chunks are chained so that references handed out earlier
+/// stay valid until the arena is destroyed.
+///
+/// Relies on `Vec`, so it is only available on hosted builds.
+#[cfg(not(feature = "no_std"))]
+pub mod arena {
+    use super::TypedArray;
+    use core::cell::{Cell, RefCell};
+    use core::cmp;
+    use core::mem::{align_of, needs_drop, size_of};
+    use core::{ptr, slice};
+
+    /// Round `addr` up to the next multiple of `align` (a power of two).
+    #[inline]
+    fn align_up(addr: usize, align: usize) -> usize {
+        (addr + align - 1) & !(align - 1)
+    }
+
+    /// Pick the size of the next chunk: at least `needed` bytes, otherwise
+    /// double the previous chunk so that repeated growth amortizes.
+    fn next_chunk_bytes(prev: usize, needed: usize) -> usize {
+        cmp::max(needed, cmp::max(prev.wrapping_mul(2), super::mmap::page_size()))
+    }
+
+    /// An arena for `Copy`/drop-free values: allocation is a pointer bump and
+    /// nothing is ever dropped individually.
+    pub struct DroplessArena {
+        /// Next free byte in the current chunk.
+        ptr: Cell<*mut u8>,
+        /// One past the last usable byte in the current chunk.
+        end: Cell<*mut u8>,
+        /// All chunks, kept alive until `Drop`.
+        chunks: RefCell<Vec<TypedArray<u8>>>,
+    }
+
+    impl DroplessArena {
+        pub fn new() -> DroplessArena {
+            DroplessArena {
+                ptr: Cell::new(ptr::null_mut()),
+                end: Cell::new(ptr::null_mut()),
+                chunks: RefCell::new(Vec::new()),
+            }
+        }
+
+        /// Bump the cursor, rounding up to `align` first, and map a fresh chunk
+        /// when the current one cannot satisfy the request.
+        fn alloc_raw(&self, size: usize, align: usize) -> *mut u8 {
+            // Zero-sized allocations occupy no chunk memory; hand back a
+            // dangling-but-aligned pointer instead of bumping from null.
+            if size == 0 {
+                return align as *mut u8;
+            }
+            loop {
+                let start = align_up(self.ptr.get() as usize, align);
+                let new_ptr = start + size;
+                if new_ptr <= self.end.get() as usize {
+                    self.ptr.set(new_ptr as *mut u8);
+                    return start as *mut u8;
+                }
+                self.grow(size + align);
+            }
+        }
+
+        fn grow(&self, needed: usize) {
+            let mut chunks = self.chunks.borrow_mut();
+            let prev = chunks.last().map(|c| c.capacity()).unwrap_or(0);
+            let chunk = TypedArray::<u8>::new(next_chunk_bytes(prev, needed));
+            let cap = chunk.capacity();
+            let base = unsafe { chunk.get(0) };
+            self.ptr.set(base);
+            self.end.set(unsafe { base.offset(cap as isize) });
+            chunks.push(chunk);
+        }
+
+        /// Allocate `value`, returning a mutable reference valid until the
+        /// arena is dropped. Panics if `T` needs dropping.
+        pub fn alloc<T>(&self, value: T) -> &mut T {
+            assert!(
+                !needs_drop::<T>(),
+                "DroplessArena cannot allocate types that need Drop"
+            );
+            let mem = self.alloc_raw(size_of::<T>(), align_of::<T>()) as *mut T;
+            unsafe {
+                ptr::write(mem, value);
+                &mut *mem
+            }
+        }
+
+        /// Copy `src` into the arena and return the new slice.
+        pub fn alloc_slice<T: Copy>(&self, src: &[T]) -> &mut [T] {
+            assert!(
+                !needs_drop::<T>(),
+                "DroplessArena cannot allocate types that need Drop"
+            );
+            if src.is_empty() {
+                return &mut [];
+            }
+            let mem = self.alloc_raw(size_of::<T>() * src.len(), align_of::<T>()) as *mut T;
+            unsafe {
+                ptr::copy_nonoverlapping(src.as_ptr(), mem, src.len());
+                slice::from_raw_parts_mut(mem, src.len())
+            }
+        }
+
+        /// Collect an iterator into the arena and return the new slice.
+        pub fn alloc_from_iter<T: Copy, I: IntoIterator<Item = T>>(&self, iter: I) -> &mut [T] {
+            let vec: Vec<T> = iter.into_iter().collect();
+            self.alloc_slice(&vec)
+        }
+    }
+
+    impl Default for DroplessArena {
+        fn default() -> DroplessArena {
+            DroplessArena::new()
+        }
+    }
+
+    impl Drop for DroplessArena {
+        fn drop(&mut self) {
+            for chunk in self.chunks.borrow().iter() {
+                unsafe { chunk.destroy() }
+            }
+        }
+    }
+
+    /// A typed arena that additionally records the objects it allocates and
+    /// runs their `Drop` when the arena itself is dropped.
+    pub struct TypedArena<T> {
+        /// Next free slot in the current chunk.
+        ptr: Cell<*mut T>,
+        /// One past the last usable slot in the current chunk.
+        end: Cell<*mut T>,
+        /// Base of the current chunk.
+        start: Cell<*mut T>,
+        /// All chunks, kept alive until `Drop`.
+        chunks: RefCell<Vec<super::TypedArray<T>>>,
+        /// Finalized element counts for every chunk but the current one.
+        filled: RefCell<Vec<usize>>,
+        /// Count of zero-sized values allocated; they occupy no chunk memory
+        /// so their destructors are tracked separately.
+        zst_count: Cell<usize>,
+    }
+
+    impl<T> TypedArena<T> {
+        pub fn new() -> TypedArena<T> {
+            TypedArena {
+                ptr: Cell::new(ptr::null_mut()),
+                end: Cell::new(ptr::null_mut()),
+                start: Cell::new(ptr::null_mut()),
+                chunks: RefCell::new(Vec::new()),
+                filled: RefCell::new(Vec::new()),
+                zst_count: Cell::new(0),
+            }
+        }
+
+        fn grow(&self, needed: usize) {
+            let mut chunks = self.chunks.borrow_mut();
+            // Finalize the element count for the chunk we are leaving.
+            if !self.start.get().is_null() {
+                let count = (self.ptr.get() as usize - self.start.get() as usize)
+                    / cmp::max(size_of::<T>(), 1);
+                self.filled.borrow_mut().push(count);
+            }
+            let prev = chunks.last().map(|c| c.capacity()).unwrap_or(0);
+            let cap = cmp::max(needed, cmp::max(prev.wrapping_mul(2), 1));
+            let chunk = super::TypedArray::<T>::new(cap);
+            let real_cap = chunk.capacity();
+            let base = unsafe { chunk.get(0) };
+            self.start.set(base);
+            self.ptr.set(base);
+            self.end.set(unsafe { base.offset(real_cap as isize) });
+            chunks.push(chunk);
+        }
+
+        /// Allocate `value`, running its destructor when the arena is dropped.
+        pub fn alloc(&self, value: T) -> &mut T {
+            if size_of::<T>() == 0 {
+                // ZSTs take no space: just count them so `Drop` runs the right
+                // number of destructors, and return a dangling aligned ref.
+                self.zst_count.set(self.zst_count.get() + 1);
+                let mem = align_of::<T>() as *mut T;
+                unsafe {
+                    ptr::write(mem, value);
+                    return &mut *mem;
+                }
+            }
+            if self.ptr.get() == self.end.get() {
+                self.grow(1);
+            }
+            let mem = self.ptr.get();
+            unsafe {
+                ptr::write(mem, value);
+                self.ptr.set(mem.offset(1));
+                &mut *mem
+            }
+        }
+
+        /// Move an iterator's items into the arena and return them as one
+        /// contiguous slice. Items are buffered first so that the result never
+        /// straddles a chunk boundary.
+        pub fn alloc_from_iter<I: IntoIterator<Item = T>>(&self, iter: I) -> &mut [T] {
+            let mut vec: Vec<T> = iter.into_iter().collect();
+            let len = vec.len();
+            if len == 0 {
+                return &mut [];
+            }
+            if size_of::<T>() == 0 {
+                // Count the ZSTs for `Drop` and leak the (heap-free) `Vec`
+                // shell so its elements are not dropped here.
+                self.zst_count.set(self.zst_count.get() + len);
+                core::mem::forget(vec);
+                let mem = align_of::<T>() as *mut T;
+                return unsafe { slice::from_raw_parts_mut(mem, len) };
+            }
+            let elem = cmp::max(size_of::<T>(), 1);
+            let remaining = (self.end.get() as usize - self.ptr.get() as usize) / elem;
+            if remaining < len {
+                self.grow(len);
+            }
+            let base = self.ptr.get();
+            unsafe {
+                ptr::copy_nonoverlapping(vec.as_ptr(), base, len);
+                // Ownership of the elements has moved into the arena.
+                vec.set_len(0);
+                self.ptr.set(base.offset(len as isize));
+                slice::from_raw_parts_mut(base, len)
+            }
+        }
+    }
+
+    impl<T> Default for TypedArena<T> {
+        fn default() -> TypedArena<T> {
+            TypedArena::new()
+        }
+    }
+
+    impl<T> Drop for TypedArena<T> {
+        fn drop(&mut self) {
+            if size_of::<T>() == 0 {
+                // ZSTs live at a dangling aligned address; drop each counted one.
+                let mem = align_of::<T>() as *mut T;
+                for _ in 0..self.zst_count.get() {
+                    unsafe { ptr::drop_in_place(mem) }
+                }
+                return;
+            }
+            let chunks = self.chunks.borrow();
+            let filled = self.filled.borrow();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let count = if i < filled.len() {
+                    filled[i]
+                } else {
+                    // The current (last) chunk: its live count is the cursor.
+                    (self.ptr.get() as usize - self.start.get() as usize)
+                        / cmp::max(size_of::<T>(), 1)
+                };
+                for n in 0..count {
+                    unsafe { ptr::drop_in_place(chunk.get(n)) }
+                }
+                unsafe { chunk.destroy() }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[derive(Debug)]
@@ -255,4 +837,169 @@ mod tests {
         alloc_assert_eq!(l_u, 1);
     }
 
+    #[test]
+    fn try_new_and_try_get() {
+        let arr = TypedArray::<usize>::try_new(8).expect("mmap should not fail");
+        alloc_assert!(arr.len() >= 8);
+        alloc_assert!(arr.try_get(7).is_some());
+        alloc_assert!(arr.try_get(arr.len()).is_none());
+        unsafe { arr.destroy() };
+    }
+
+    #[test]
+    fn lazy_non_initializing_api() {
+        let mut l = Lazy::<DefaultInit<usize>>::new(());
+        alloc_assert!(!l.filled());
+        alloc_assert!(l.try_get().is_none());
+        alloc_assert_eq!(l.get_or_try_init(|_| Ok::<_, ()>(DefaultInit(5))).unwrap().0, 5);
+        alloc_assert!(l.filled());
+        // A second call does not re-run the initializer.
+        alloc_assert_eq!(l.get_or_try_init(|_| Ok::<_, ()>(DefaultInit(9))).unwrap().0, 5);
+        let prev = l.replace(DefaultInit(7));
+        alloc_assert_eq!(prev.unwrap().0, 5);
+        alloc_assert_eq!(l.try_get().unwrap().0, 7);
+    }
+
+    #[test]
+    fn sync_lazy_initializes_once() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+        use std::thread;
+
+        static INITS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        struct Counter(usize);
+        impl LazyInitializable for Counter {
+            type Params = ();
+            fn init(_p: &()) -> Self {
+                Counter(INITS.fetch_add(1, Ordering::SeqCst))
+            }
+        }
+
+        let shared = Arc::new(SyncLazy::<Counter>::new(()));
+        let handles: Vec<_> = (0..16)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || shared.0)
+            })
+            .collect();
+        for h in handles {
+            // Every thread observes the single initialization's result.
+            alloc_assert_eq!(h.join().unwrap(), 0);
+        }
+        alloc_assert_eq!(INITS.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn typed_array_placement_api() {
+        let arr = TypedArray::<u32>::new(16);
+        // Untouched slots read as zero because the mmap region is zeroed.
+        alloc_assert_eq!(unsafe { *arr.get(0) }, 0);
+
+        arr.write(0, 11);
+        arr.copy_from_slice(1, &[22, 33, 44]);
+        alloc_assert_eq!(unsafe { *arr.get(0) }, 11);
+        alloc_assert_eq!(unsafe { *arr.get(3) }, 44);
+
+        // A byte offset that is a multiple of `align_of::<u32>()` succeeds.
+        let bytes = [1u8, 0, 0, 0];
+        let next = arr.copy_align_from_bytes(8, &bytes).unwrap();
+        alloc_assert_eq!(next, 12);
+        alloc_assert_eq!(unsafe { *arr.get(2) }, 1);
+        // A misaligned offset is rejected rather than performing an unaligned
+        // write.
+        alloc_assert_eq!(arr.copy_align_from_bytes(1, &bytes), Err(UnalignedError));
+        unsafe { arr.destroy() };
+    }
+
+    #[test]
+    fn dropless_arena_alloc_and_slice() {
+        use super::arena::DroplessArena;
+        let arena = DroplessArena::new();
+        let a = arena.alloc(42usize);
+        let b = arena.alloc(7usize);
+        alloc_assert_eq!(*a, 42);
+        alloc_assert_eq!(*b, 7);
+        let s = arena.alloc_slice(&[1u32, 2, 3, 4]);
+        alloc_assert_eq!(s, &mut [1, 2, 3, 4]);
+        let from_iter = arena.alloc_from_iter(0u16..5);
+        alloc_assert_eq!(from_iter, &mut [0, 1, 2, 3, 4]);
+        // A drop-free ZST yields an aligned, non-null reference rather than
+        // bumping from null.
+        let unit: &mut () = arena.alloc(());
+        alloc_assert!(unit as *mut () as usize != 0);
+    }
+
+    #[test]
+    fn typed_arena_runs_drop() {
+        use super::arena::TypedArena;
+        use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+        static DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+        // A non-zero-sized payload so the bump-and-chain path is exercised.
+        struct Noisy(#[allow(dead_code)] usize);
+        impl Drop for Noisy {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        {
+            let arena = TypedArena::new();
+            for i in 0..1000 {
+                arena.alloc(Noisy(i));
+            }
+            alloc_assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+        }
+        alloc_assert_eq!(DROPS.load(Ordering::SeqCst), 1000);
+    }
+
+    #[test]
+    fn typed_arena_runs_drop_for_zst() {
+        use super::arena::TypedArena;
+        use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+        static ZST_DROPS: AtomicUsize = ATOMIC_USIZE_INIT;
+        // A zero-sized type: the arena must count these rather than bump a
+        // cursor, and still run every destructor.
+        struct Zst;
+        impl Drop for Zst {
+            fn drop(&mut self) {
+                ZST_DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        {
+            let arena = TypedArena::new();
+            for _ in 0..1000 {
+                arena.alloc(Zst);
+            }
+            alloc_assert_eq!(ZST_DROPS.load(Ordering::SeqCst), 0);
+        }
+        alloc_assert_eq!(ZST_DROPS.load(Ordering::SeqCst), 1000);
+    }
+
+    #[test]
+    fn capacity_uses_slack() {
+        // `new` leaves `len` at the requested count; `capacity` exposes the
+        // page-rounded slack without redefining `len`.
+        let requested = TypedArray::<usize>::new(1);
+        alloc_assert_eq!(requested.len(), 1);
+        alloc_assert!(requested.capacity() >= 1);
+        unsafe { requested.destroy() };
+
+        // `with_capacity` opts in to the slack: every extra slot is writable.
+        let arr = TypedArray::<usize>::with_capacity(1);
+        let cap = arr.capacity();
+        alloc_assert!(cap >= 1);
+        alloc_assert_eq!(arr.len(), cap);
+        for n in 0..cap {
+            unsafe { *arr.get(n) = n };
+        }
+        for n in 0..cap {
+            alloc_assert_eq!(unsafe { *arr.get(n) }, n);
+        }
+        unsafe { arr.destroy() };
+    }
+
 }